@@ -0,0 +1,14 @@
+//! Compresses the embedded SPDX license-text cache at build time, the same
+//! way cargo-deny ships its `spdx_cache.bin.zstd`.
+
+use std::env;
+use std::fs;
+use std::path::Path;
+
+fn main() {
+    let out_dir = env::var("OUT_DIR").unwrap();
+    let src = fs::read("licenses/spdx_texts.json").expect("missing licenses/spdx_texts.json");
+    let compressed = zstd::encode_all(&src[..], 19).expect("failed to compress spdx cache");
+    fs::write(Path::new(&out_dir).join("spdx_cache.bin.zst"), compressed).unwrap();
+    println!("cargo:rerun-if-changed=licenses/spdx_texts.json");
+}
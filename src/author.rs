@@ -0,0 +1,314 @@
+//! Configurable author/year resolution for license headers.
+//!
+//! `first_author` used to hardcode "most blame hunks by name", which
+//! misbehaves on renamed files, co-authors, and repos without git. This
+//! module adds a `[author]` config section to pick the resolution strategy,
+//! canonicalize identities via `.mailmap`, and fall back to static defaults
+//! outside a git checkout.
+
+use crate::Config;
+use chrono::Datelike;
+use failure::{format_err, Error};
+use serde::Deserialize;
+use std::path::Path;
+
+#[derive(Deserialize, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "kebab-case")]
+pub enum AuthorMode {
+    /// The author of the oldest-timestamped surviving blame hunk. This is
+    /// *not* necessarily whoever created the file: if every original line
+    /// has since been edited, blame has nothing left to attribute to the
+    /// original author, and this picks the earliest remaining edit instead.
+    FirstCommit,
+    /// The author credited with the most blamed lines.
+    #[default]
+    MostLines,
+    /// The author of the newest-timestamped surviving blame hunk. A later
+    /// commit that only deleted lines leaves no hunk and is not considered.
+    Latest,
+}
+
+#[derive(Deserialize, Default)]
+pub struct AuthorConfig {
+    #[serde(default)]
+    pub mode: AuthorMode,
+
+    /// Used in place of git blame when the file is untracked or the path
+    /// isn't inside a git checkout at all.
+    #[serde(default)]
+    pub default_author: Option<String>,
+
+    /// Used alongside `default_author`; defaults to the current year if unset.
+    #[serde(default)]
+    pub default_year: Option<String>,
+}
+
+/// The resolved identity/date values available to license template placeholders.
+pub struct AuthorInfo {
+    pub name: String,
+    pub email: Option<String>,
+    pub year: String,
+    pub year_range: String,
+}
+
+pub fn resolve_author<P: AsRef<Path>>(config: &Config, path: P) -> Result<AuthorInfo, Error> {
+    let path = path.as_ref();
+    match git2::Repository::discover(path) {
+        Ok(repo) => resolve_from_git(config, &repo, path).or_else(|_| resolve_default(config)),
+        Err(_) => resolve_default(config),
+    }
+}
+
+fn resolve_default(config: &Config) -> Result<AuthorInfo, Error> {
+    let name = config.author.default_author.clone().ok_or_else(|| {
+        format_err!("not a git repository (or file untracked) and no [author].default_author configured")
+    })?;
+    let year = config
+        .author
+        .default_year
+        .clone()
+        .unwrap_or_else(|| chrono::Utc::now().year().to_string());
+    Ok(AuthorInfo {
+        name,
+        email: None,
+        year: year.clone(),
+        year_range: year,
+    })
+}
+
+fn signature_year(sig: &git2::Signature) -> i32 {
+    chrono::DateTime::from_timestamp(sig.when().seconds(), 0)
+        .expect("signature timestamp out of range")
+        .year()
+}
+
+fn mailmapped(repo: &git2::Repository, sig: git2::Signature<'static>) -> git2::Signature<'static> {
+    repo.mailmap()
+        .and_then(|mailmap| mailmap.resolve_signature(&sig))
+        .unwrap_or(sig)
+}
+
+fn resolve_from_git(config: &Config, repo: &git2::Repository, path: &Path) -> Result<AuthorInfo, Error> {
+    let blame = repo.blame_file(path, None)?;
+    if blame.is_empty() {
+        return Err(format_err!("file has no blame history (untracked?)"));
+    }
+
+    let mut years: Vec<i32> = Vec::new();
+    let chosen_sig = match config.author.mode {
+        AuthorMode::MostLines => {
+            let mut counter: std::collections::HashMap<String, (usize, git2::Signature<'static>)> =
+                std::collections::HashMap::new();
+            for hunk in blame.iter() {
+                let sig = hunk.orig_signature();
+                years.push(signature_year(&sig));
+                let key = format!("{}<{}>", sig.name().unwrap_or(""), sig.email().unwrap_or(""));
+                let entry = counter.entry(key).or_insert((0, sig.to_owned()));
+                entry.0 += hunk.lines_in_hunk();
+            }
+            counter
+                .into_values()
+                .max_by_key(|(lines, _)| *lines)
+                .map(|(_, sig)| sig)
+                .ok_or_else(|| format_err!("cannot find author info"))?
+        }
+        AuthorMode::FirstCommit | AuthorMode::Latest => {
+            let mut best: Option<git2::Signature<'static>> = None;
+            let mut best_time = None;
+            for hunk in blame.iter() {
+                let sig = hunk.orig_signature();
+                let time = sig.when().seconds();
+                years.push(signature_year(&sig));
+                let better = match (best_time, config.author.mode) {
+                    (None, _) => true,
+                    (Some(t), AuthorMode::FirstCommit) => time < t,
+                    (Some(t), AuthorMode::Latest) => time > t,
+                    (Some(_), AuthorMode::MostLines) => unreachable!(),
+                };
+                if better {
+                    best_time = Some(time);
+                    best = Some(sig.to_owned());
+                }
+            }
+            best.ok_or_else(|| format_err!("cannot find author info"))?
+        }
+    };
+
+    let year = signature_year(&chosen_sig);
+    let sig = mailmapped(repo, chosen_sig);
+    let name = sig
+        .name()
+        .ok_or_else(|| format_err!("author name is not valid UTF-8"))?
+        .to_string();
+    let email = sig.email().map(|e| e.to_string());
+
+    let min_year = *years.iter().min().unwrap();
+    let max_year = *years.iter().max().unwrap();
+    let year_range = if min_year == max_year {
+        min_year.to_string()
+    } else {
+        format!("{}-{}", min_year, max_year)
+    };
+
+    Ok(AuthorInfo {
+        name,
+        email,
+        year: year.to_string(),
+        year_range,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::Mutex;
+
+    // `resolve_from_git` blames whatever path it's handed, same as real
+    // callers passing a path relative to the repo root; these tests run
+    // against a throwaway repo via `set_current_dir`, so serialize them to
+    // avoid racing on the shared process cwd.
+    static CWD_LOCK: Mutex<()> = Mutex::new(());
+
+    struct TempRepo {
+        dir: std::path::PathBuf,
+        prev_cwd: std::path::PathBuf,
+        _lock: std::sync::MutexGuard<'static, ()>,
+    }
+
+    impl TempRepo {
+        fn new() -> Self {
+            let lock = CWD_LOCK.lock().unwrap_or_else(|e| e.into_inner());
+            let dir = std::env::temp_dir().join(format!(
+                "license-checker-author-test-{}-{}",
+                std::process::id(),
+                line!()
+            ));
+            std::fs::create_dir_all(&dir).unwrap();
+            git2::Repository::init(&dir).unwrap();
+            let prev_cwd = std::env::current_dir().unwrap();
+            std::env::set_current_dir(&dir).unwrap();
+            TempRepo { dir, prev_cwd, _lock: lock }
+        }
+
+        /// Writes `file` with `contents` and commits it as `name`/`email` at
+        /// `unix_time`, on top of whatever HEAD currently is.
+        fn commit(&self, file: &str, contents: &str, name: &str, email: &str, unix_time: i64) {
+            std::fs::write(self.dir.join(file), contents).unwrap();
+            let repo = git2::Repository::open(&self.dir).unwrap();
+            let mut index = repo.index().unwrap();
+            index.add_path(Path::new(file)).unwrap();
+            index.write().unwrap();
+            let tree = repo.find_tree(index.write_tree().unwrap()).unwrap();
+            let sig = git2::Signature::new(name, email, &git2::Time::new(unix_time, 0)).unwrap();
+            let parent = repo.head().ok().and_then(|h| h.peel_to_commit().ok());
+            let parents: Vec<&git2::Commit> = parent.iter().collect();
+            repo.commit(Some("HEAD"), &sig, &sig, "license-checker test commit", &tree, &parents).unwrap();
+        }
+    }
+
+    impl Drop for TempRepo {
+        fn drop(&mut self) {
+            std::env::set_current_dir(&self.prev_cwd).unwrap();
+            let _ = std::fs::remove_dir_all(&self.dir);
+        }
+    }
+
+    fn config_with_author(author: AuthorConfig) -> Config {
+        Config {
+            template: String::new(),
+            template_path: None,
+            spdx: None,
+            newline_after_shebang: false,
+            newline_after_template: false,
+            placeholder_defaults: std::collections::HashMap::new(),
+            exclude: Vec::new(),
+            include: Vec::new(),
+            allowed: Vec::new(),
+            exceptions: std::collections::HashMap::new(),
+            author,
+            langs: std::collections::HashMap::new(),
+        }
+    }
+
+    fn config_with_mode(mode: AuthorMode) -> Config {
+        config_with_author(AuthorConfig { mode, default_author: None, default_year: None })
+    }
+
+    const ALICE_2019: i64 = 1_546_300_800; // 2019-01-01T00:00:00Z
+    const BOB_2024: i64 = 1_704_067_200; // 2024-01-01T00:00:00Z
+
+    /// Sets up a file with two lines from Alice (2019) and a third line
+    /// appended by Bob (2024), so Alice holds a 2-line majority but Bob's
+    /// commit is the most recent.
+    fn alice_majority_repo() -> TempRepo {
+        let repo = TempRepo::new();
+        repo.commit("file.rs", "line1\nline2\n", "Alice", "alice@example.com", ALICE_2019);
+        repo.commit("file.rs", "line1\nline2\nline3\n", "Bob", "bob@example.com", BOB_2024);
+        repo
+    }
+
+    #[test]
+    fn most_lines_picks_the_majority_author_and_their_own_year() {
+        let repo = alice_majority_repo();
+        let config = config_with_mode(AuthorMode::MostLines);
+        let info = resolve_author(&config, "file.rs").unwrap();
+        assert_eq!(info.name, "Alice");
+        // Regression test: year must come from the chosen author's own
+        // commit, not the newest commit across every blame hunk.
+        assert_eq!(info.year, "2019");
+        assert_eq!(info.year_range, "2019-2024");
+    }
+
+    #[test]
+    fn first_commit_picks_the_earliest_author() {
+        let repo = alice_majority_repo();
+        let config = config_with_mode(AuthorMode::FirstCommit);
+        let info = resolve_author(&config, "file.rs").unwrap();
+        assert_eq!(info.name, "Alice");
+        assert_eq!(info.year, "2019");
+    }
+
+    #[test]
+    fn latest_picks_the_most_recent_author() {
+        let repo = alice_majority_repo();
+        let config = config_with_mode(AuthorMode::Latest);
+        let info = resolve_author(&config, "file.rs").unwrap();
+        assert_eq!(info.name, "Bob");
+        assert_eq!(info.year, "2024");
+    }
+
+    #[test]
+    fn mailmap_canonicalizes_the_resolved_identity() {
+        let repo = TempRepo::new();
+        repo.commit("file.rs", "line1\n", "Old Name", "old@example.com", ALICE_2019);
+        std::fs::write(repo.dir.join(".mailmap"), "New Name <old@example.com>\n").unwrap();
+
+        let config = config_with_mode(AuthorMode::Latest);
+        let info = resolve_author(&config, "file.rs").unwrap();
+        assert_eq!(info.name, "New Name");
+    }
+
+    #[test]
+    fn resolve_default_uses_configured_fallback_outside_a_repo() {
+        let dir = std::env::temp_dir().join(format!(
+            "license-checker-author-test-nogit-{}-{}",
+            std::process::id(),
+            line!()
+        ));
+        std::fs::create_dir_all(&dir).unwrap();
+        let file = dir.join("file.rs");
+        std::fs::write(&file, "fn main() {}").unwrap();
+
+        let config = config_with_author(AuthorConfig {
+            mode: AuthorMode::MostLines,
+            default_author: Some("Fallback Author".to_string()),
+            default_year: Some("2020".to_string()),
+        });
+        let info = resolve_author(&config, &file).unwrap();
+        std::fs::remove_dir_all(&dir).unwrap();
+
+        assert_eq!(info.name, "Fallback Author");
+        assert_eq!(info.year, "2020");
+        assert_eq!(info.year_range, "2020");
+    }
+}
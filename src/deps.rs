@@ -0,0 +1,136 @@
+//! `deps` subcommand: audits the Cargo dependency graph against an SPDX
+//! license allowlist, mirroring rustc's `tidy/deps.rs` and cargo-deny.
+
+use crate::Config;
+use failure::{format_err, Error};
+
+/// Parses an SPDX expression like `MIT OR Apache-2.0`, `Unlicense/MIT` or
+/// `MIT AND Apache-2.0` into a list of OR'd clauses, each an AND'd list of
+/// license identifiers: satisfying *any one* clause in full satisfies the
+/// whole expression. `/` is the legacy crates.io separator and means `OR`.
+///
+/// This isn't a full SPDX expression parser (no operator precedence across
+/// mixed `AND`/`OR`, `WITH` exceptions, etc.) but it's enough to tell "either
+/// license is fine" apart from "both licenses' terms apply", which is the
+/// distinction that actually matters for an allowlist check.
+fn parse_spdx_expr(expr: &str) -> Vec<Vec<String>> {
+    let unparenthesized: String = expr.chars().filter(|c| *c != '(' && *c != ')').collect();
+    let normalized = unparenthesized.replace('/', " OR ");
+    normalized
+        .split(" OR ")
+        .map(|clause| {
+            clause
+                .split(" AND ")
+                .map(|id| id.trim().to_string())
+                .filter(|id| !id.is_empty())
+                .collect::<Vec<_>>()
+        })
+        .filter(|clause: &Vec<String>| !clause.is_empty())
+        .collect()
+}
+
+/// True if `license` is satisfied by `allowed`: every identifier in `allowed`
+/// (each entry may itself be an expression, e.g. `"MIT OR Apache-2.0"`) is
+/// flattened into one set of acceptable ids, and `license` is compliant if
+/// at least one of its OR'd clauses has every one of its AND'd ids in that
+/// set. This lets a flat `allowed = ["MIT", "Apache-2.0"]` cover a
+/// dual-licensed dependency declaring `"MIT OR Apache-2.0"` without the
+/// admin having to enumerate every exact combined expression.
+fn license_is_allowed(license: &str, allowed: &[String]) -> bool {
+    let allowed_ids: std::collections::HashSet<String> =
+        allowed.iter().flat_map(|candidate| parse_spdx_expr(candidate).into_iter().flatten()).collect();
+    parse_spdx_expr(license)
+        .iter()
+        .any(|clause| clause.iter().all(|id| allowed_ids.contains(id)))
+}
+
+/// Checks every crate in the dependency graph rooted at the current
+/// directory's `Cargo.toml` against `config.allowed` / `config.exceptions`.
+/// Prints one line per violation and returns an error if any were found.
+pub fn check(config: &Config) -> Result<(), Error> {
+    let metadata = cargo_metadata::MetadataCommand::new().exec()?;
+
+    let mut violations = Vec::new();
+
+    for package in &metadata.packages {
+        let license = package.license.clone().unwrap_or_default();
+        // `license-file` is a valid alternative to the `license` field (e.g.
+        // a non-SPDX or proprietary text); we can't check its contents
+        // against [allowed], so its mere presence satisfies the policy.
+        let has_license_file = package.license_file.is_some();
+        let compliant =
+            has_license_file || (!license.is_empty() && license_is_allowed(&license, &config.allowed));
+
+        if let Some(reason) = config.exceptions.get(&package.name) {
+            if compliant {
+                violations.push(format!(
+                    "{}: exception \"{}\" is stale, license \"{}\" now satisfies policy",
+                    package.name, reason, license
+                ));
+            }
+            continue;
+        }
+
+        if !compliant {
+            let shown = if license.is_empty() { "<none>" } else { &license };
+            violations.push(format!(
+                "{}: license \"{}\" is not in [allowed] and has no [exceptions] entry",
+                package.name, shown
+            ));
+        }
+    }
+
+    for violation in &violations {
+        println!("{}", violation);
+    }
+
+    if violations.is_empty() {
+        Ok(())
+    } else {
+        Err(format_err!("{} dependency license violation(s)", violations.len()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn slash_is_treated_as_or() {
+        assert_eq!(parse_spdx_expr("Unlicense/MIT"), parse_spdx_expr("Unlicense OR MIT"));
+    }
+
+    #[test]
+    fn strips_enclosing_parentheses() {
+        assert_eq!(parse_spdx_expr("(MIT OR Apache-2.0)"), parse_spdx_expr("MIT OR Apache-2.0"));
+    }
+
+    #[test]
+    fn flat_allowlist_covers_a_dual_licensed_dependency() {
+        // The common case this check exists for: a crate declaring "MIT OR
+        // Apache-2.0" should be covered by listing each id separately,
+        // without the admin enumerating every combined expression.
+        let allowed = vec!["MIT".to_string(), "Apache-2.0".to_string()];
+        assert!(license_is_allowed("MIT OR Apache-2.0", &allowed));
+        assert!(license_is_allowed("Apache-2.0/MIT", &allowed));
+        assert!(!license_is_allowed("GPL-3.0", &allowed));
+    }
+
+    #[test]
+    fn or_is_satisfied_by_any_one_allowed_license() {
+        let allowed = vec!["MIT".to_string()];
+        assert!(license_is_allowed("MIT OR GPL-3.0", &allowed));
+    }
+
+    #[test]
+    fn and_requires_every_license_to_be_allowed() {
+        // Regression test: AND means both licenses' terms apply, which is a
+        // strictly stronger requirement than OR and must not be conflated
+        // with it just because both tokens get filtered out the same way.
+        let allowed = vec!["MIT".to_string()];
+        assert!(!license_is_allowed("MIT AND GPL-3.0", &allowed));
+
+        let allowed_both = vec!["MIT".to_string(), "Apache-2.0".to_string()];
+        assert!(license_is_allowed("MIT AND Apache-2.0", &allowed_both));
+    }
+}
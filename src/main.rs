@@ -1,11 +1,63 @@
 #![feature(label_break_value)]
+mod author;
+mod deps;
+mod spdx_cache;
+
 use clap::{App, Arg, SubCommand};
 use failure::{format_err, Error};
 use serde::Deserialize;
 use std::collections::HashMap;
 use std::io::BufRead;
-use std::path::Path;
-use chrono::Datelike;
+use std::path::{Path, PathBuf};
+use rayon::prelude::*;
+
+/// Walks `path`, returning every file with a recognized extension.
+///
+/// If `path` is a file, it is returned as-is (even if its extension is not
+/// recognized, matching the previous single-file behavior). If it is a
+/// directory, it is walked recursively honoring `.gitignore` plus the
+/// `exclude`/`include` glob lists in `Config`, and files whose extension
+/// isn't declared by any `Lang` are skipped rather than erroring.
+fn walk_files<P: AsRef<Path>>(config: &Config, path: P) -> Result<Vec<PathBuf>, Error> {
+    let path = path.as_ref();
+    if !path.is_dir() {
+        return Ok(vec![path.to_path_buf()]);
+    }
+
+    let mut overrides = ignore::overrides::OverrideBuilder::new(path);
+    // `Override::matched` treats any whitelist (non-`!`) pattern as making the
+    // whole walk include-only: everything that doesn't match one of the
+    // overrides is then considered ignored. Since `include` is meant to
+    // re-include specific paths on top of a normal walk, not replace it, seed
+    // a catch-all whitelist pattern before the exclude/include patterns so
+    // ordinary files keep walking and only `exclude` narrows them back down.
+    if !config.include.is_empty() {
+        overrides.add("*")?;
+    }
+    for pattern in &config.exclude {
+        overrides.add(&format!("!{}", pattern))?;
+    }
+    for pattern in &config.include {
+        overrides.add(pattern)?;
+    }
+    let overrides = overrides.build()?;
+
+    let mut files = Vec::new();
+    for entry in ignore::WalkBuilder::new(path).overrides(overrides).build() {
+        let entry = entry?;
+        if entry.file_type().is_some_and(|t| t.is_file()) && has_recognized_extension(config, entry.path()) {
+            files.push(entry.path().to_path_buf());
+        }
+    }
+    Ok(files)
+}
+
+fn has_recognized_extension(config: &Config, path: &Path) -> bool {
+    match path.extension().and_then(|e| e.to_str()) {
+        Some(ext) => config.langs.values().any(|l| l.extensions.iter().any(|e| e == ext)),
+        None => false,
+    }
+}
 
 fn get_comment<P: AsRef<Path>>(config: &Config, path: P) -> Result<&str, Error> {
     let path = path.as_ref();
@@ -22,23 +74,122 @@ fn get_comment<P: AsRef<Path>>(config: &Config, path: P) -> Result<&str, Error>
     return Err(format_err!("unsupported file extension: {}", ext));
 }
 
-fn first_author<P: AsRef<Path>>(path: P) -> Result<String, Error> {
-    let path = path.as_ref();
-    let repo = git2::Repository::discover(path)?;
-    
-    let blame = repo.blame_file(path, None)?;
-    let mut counter: HashMap<String, usize> = HashMap::new();
-    for hunk in blame.iter() {
-        let sig = hunk.orig_signature();
-        if let Some(name) = sig.name() {
-            *counter.entry(name.to_string()).or_default() += 1;
+/// A template line broken into literal text and `{...}` placeholder spans.
+enum TemplateToken {
+    Literal(String),
+    Placeholder(String),
+}
+
+/// Splits a template line into literal and placeholder tokens.
+///
+/// `{...}` marks a placeholder whose inner text is either one of the builtin
+/// aliases (`author`, `year`, `year_range`, `email`) or, for anything else, a regular expression
+/// fragment to be spliced verbatim into the compiled pattern. `\{`, `\}` and
+/// `\\` match literal braces/backslash.
+fn parse_template_line(line: &str) -> Vec<TemplateToken> {
+    let mut tokens = Vec::new();
+    let mut literal = String::new();
+    let chars: Vec<char> = line.chars().collect();
+    let mut i = 0;
+    while i < chars.len() {
+        match chars[i] {
+            '\\' if i + 1 < chars.len() && matches!(chars[i + 1], '{' | '}' | '\\') => {
+                literal.push(chars[i + 1]);
+                i += 2;
+            }
+            '{' => {
+                if !literal.is_empty() {
+                    tokens.push(TemplateToken::Literal(std::mem::take(&mut literal)));
+                }
+                let mut depth = 1;
+                let start = i + 1;
+                let mut j = start;
+                while j < chars.len() && depth > 0 {
+                    match chars[j] {
+                        '\\' if j + 1 < chars.len() => j += 2,
+                        '{' => {
+                            depth += 1;
+                            j += 1;
+                        }
+                        '}' => {
+                            depth -= 1;
+                            if depth > 0 {
+                                j += 1;
+                            }
+                        }
+                        _ => j += 1,
+                    }
+                }
+                tokens.push(TemplateToken::Placeholder(chars[start..j].iter().collect()));
+                i = j + 1;
+            }
+            c => {
+                literal.push(c);
+                i += 1;
+            }
         }
     }
-    if counter.is_empty() {
-        Err(format_err!("cannot find author info"))
-    } else {
-        Ok(counter.iter().max_by_key(|(_k,v)| *v).map(|(k,_v)| k).unwrap().to_string())
+    if !literal.is_empty() {
+        tokens.push(TemplateToken::Literal(literal));
     }
+    tokens
+}
+
+/// Expands a single template line (already prefixed with the comment marker)
+/// into the regex source used to match it against a file line.
+fn template_line_to_regex(line: &str) -> String {
+    let mut pattern = String::new();
+    for token in parse_template_line(line) {
+        match token {
+            TemplateToken::Literal(s) => pattern.push_str(&regex::escape(&s)),
+            TemplateToken::Placeholder(name) => match name.as_str() {
+                "author" => pattern.push_str("\\w+([ \\t\\w]*\\w)?"),
+                "year" => pattern.push_str("\\d{4}"),
+                "year_range" => pattern.push_str("\\d{4}(-\\d{4})?"),
+                "email" => pattern.push_str("\\S+@\\S+"),
+                other => {
+                    pattern.push_str("(?:");
+                    pattern.push_str(other);
+                    pattern.push(')');
+                }
+            },
+        }
+    }
+    pattern
+}
+
+/// Expands a single template line into its rendered (non-regex) form, used
+/// when writing a header with `format`. Non-alias placeholders must have a
+/// default value configured under `[placeholder_defaults]`.
+fn render_template_line(
+    config: &Config,
+    line: &str,
+    author: &author::AuthorInfo,
+) -> Result<String, Error> {
+    let mut rendered = String::new();
+    for token in parse_template_line(line) {
+        match token {
+            TemplateToken::Literal(s) => rendered.push_str(&s),
+            TemplateToken::Placeholder(name) => match name.as_str() {
+                "author" => rendered.push_str(&author.name),
+                "year" => rendered.push_str(&author.year),
+                "year_range" => rendered.push_str(&author.year_range),
+                "email" => rendered.push_str(author.email.as_deref().ok_or_else(|| {
+                    format_err!("no email available for the {{email}} placeholder")
+                })?),
+                other => {
+                    let default = config.placeholder_defaults.get(other).ok_or_else(|| {
+                        format_err!(
+                            "no default value configured for placeholder {{{}}} (add it under [placeholder_defaults])",
+                            other
+                        )
+                    })?;
+                    rendered.push_str(default);
+                }
+            },
+        }
+    }
+    Ok(rendered)
 }
 
 fn lint<P: AsRef<Path>>(config: &Config, path: P) -> Result<(), failure::Error> {
@@ -66,9 +217,7 @@ fn lint<P: AsRef<Path>>(config: &Config, path: P) -> Result<(), failure::Error>
             .next()
             .expect(&format!("Expected: {}\n  Actual: <None>\n", line));
         let line = format!("{} {}", comment, line);
-        let template_line = regex::escape(&line)
-            .replace("\\{author\\}", "\\w+([ \\t\\w]*\\w)?")
-            .replace("\\{year\\}", "\\d{4}");
+        let template_line = template_line_to_regex(&line);
         let r = regex::Regex::new(&template_line).expect("wrong template");
         if !r.is_match(&file_line) {
             return Err(format_err!(
@@ -93,7 +242,14 @@ fn lint<P: AsRef<Path>>(config: &Config, path: P) -> Result<(), failure::Error>
     Ok(())
 }
 
-fn format<P: AsRef<Path>>(config: &Config, path: P) -> Result<(), Error> {
+/// Computes the license header to insert into `path`, if it doesn't already
+/// have one. Returns `Ok(None)` when the file already passes `lint`, or
+/// `Ok(Some(new_contents))` with the rewritten file otherwise, except that a
+/// file which fails `lint` but already starts with a comment is an error
+/// rather than being rewritten. Never touches
+/// the file on disk; callers decide whether to write it, print it, or just
+/// check.
+fn format<P: AsRef<Path>>(config: &Config, path: P) -> Result<Option<String>, Error> {
     // case1. first line is #!
     // skip first line. go to case2.
     // case2. first line has no #
@@ -103,7 +259,7 @@ fn format<P: AsRef<Path>>(config: &Config, path: P) -> Result<(), Error> {
     // no action
     // ask user for safety
     let path = path.as_ref();
-    if lint(config, path).is_ok() { return Ok(()); }
+    if lint(config, path).is_ok() { return Ok(None); }
 
     let comment = get_comment(config, path)?;
 
@@ -157,32 +313,84 @@ fn format<P: AsRef<Path>>(config: &Config, path: P) -> Result<(), Error> {
     for _ in 0..insert_newline_before {
         insertion += "\n";
     }
-    let author = first_author(path)?;
-    let year = chrono::Utc::now().year().to_string();
-    let license = config
-        .template
-        .replace("{author}", &author)
-        .replace("{year}", &year);
-    for line in license.lines() {
+    let author = author::resolve_author(config, path)?;
+    for line in config.template.lines() {
         insertion += comment;
         insertion += " ";
-        insertion += line;
+        insertion += &render_template_line(config, line, &author)?;
         insertion += "\n";
     }
     for _ in 0..insert_newline_after {
         insertion += "\n";
     }
     buf.insert_str(loc, &insertion);
-    println!("{}", buf);
+    Ok(Some(buf))
+}
+
+/// Atomically rewrites `path` with `contents` by writing to a temp file in
+/// the same directory and renaming it into place. The original file's
+/// permissions (e.g. a shebang script's executable bit) are preserved.
+fn write_atomic(path: &Path, contents: &str) -> Result<(), Error> {
+    let tmp_path = path.with_file_name(format!(
+        ".{}.tmp",
+        path.file_name().expect("path has no file name").to_string_lossy()
+    ));
+    std::fs::write(&tmp_path, contents)?;
+    std::fs::set_permissions(&tmp_path, std::fs::metadata(path)?.permissions())?;
+    std::fs::rename(&tmp_path, path)?;
     Ok(())
 }
 
 #[derive(Deserialize)]
 struct Config {
+    /// The license header text, with `{author}`/`{year}`/custom placeholders.
+    /// Populated from `template_path` or `spdx` if left empty; see
+    /// `resolve_template`.
+    #[serde(default)]
     template: String,
+
+    /// Alternative to `template`: a path to a plain-text license file, read
+    /// at startup. An empty or unreadable path is a warning, not an error;
+    /// `spdx` is tried next, and startup fails only if no template results.
+    #[serde(default)]
+    template_path: Option<String>,
+
+    /// Alternative to `template`/`template_path`: an SPDX identifier (e.g.
+    /// `"Apache-2.0"`) resolved against the embedded license-text cache.
+    #[serde(default)]
+    spdx: Option<String>,
+
     newline_after_shebang: bool,
     newline_after_template: bool,
 
+    /// Default values for non-alias `{...}` placeholders, used by `format`
+    /// when writing out the rendered header (`lint` treats them as regex).
+    #[serde(default)]
+    placeholder_defaults: HashMap<String, String>,
+
+    /// Glob patterns (relative to the walked directory) to skip, in addition
+    /// to whatever `.gitignore` already excludes.
+    #[serde(default)]
+    exclude: Vec<String>,
+
+    /// Glob patterns to re-include even if matched by `exclude` or `.gitignore`.
+    #[serde(default)]
+    include: Vec<String>,
+
+    /// SPDX expressions acceptable for a dependency's declared license, e.g.
+    /// `"MIT OR Apache-2.0"`. Checked by the `deps` subcommand.
+    #[serde(default)]
+    allowed: Vec<String>,
+
+    /// Crates permitted to deviate from `allowed`, keyed by crate name with
+    /// a human-readable reason as the value.
+    #[serde(default)]
+    exceptions: HashMap<String, String>,
+
+    /// Controls how `format` picks an author/year for untemplated placeholders.
+    #[serde(default)]
+    author: author::AuthorConfig,
+
     #[serde(flatten)]
     langs: HashMap<String, Lang>,
 }
@@ -193,6 +401,35 @@ struct Lang {
     comment: String,
 }
 
+/// Fills in `config.template` from `template_path` or `spdx` when it wasn't
+/// given inline, warning (rather than erroring) on an empty/unreadable path
+/// or an unknown SPDX id, as rustfmt does for `license_template_path`.
+fn resolve_template(config: &mut Config) {
+    if !config.template.is_empty() {
+        return;
+    }
+
+    if let Some(path) = config.template_path.clone() {
+        if path.is_empty() {
+            eprintln!("warning: template_path is empty, ignoring");
+        } else {
+            match std::fs::read_to_string(&path) {
+                Ok(text) => config.template = text,
+                Err(e) => eprintln!("warning: cannot read template_path \"{}\": {}", path, e),
+            }
+        }
+    }
+
+    if config.template.is_empty() {
+        if let Some(spdx_id) = config.spdx.clone() {
+            match spdx_cache::lookup(&spdx_id) {
+                Some(text) => config.template = text,
+                None => eprintln!("warning: unknown SPDX id \"{}\", no embedded license text", spdx_id),
+            }
+        }
+    }
+}
+
 fn main() {
     let matches = App::new("License Checker")
         .version("0.1.0")
@@ -208,7 +445,7 @@ fn main() {
         .subcommand(
             SubCommand::with_name("lint")
                 .about("Check for the license header in each file")
-                .arg(Arg::with_name("path").help("a file path").takes_value(true).required(true))
+                .arg(Arg::with_name("path").help("a file or directory path").takes_value(true).required(true))
                 .arg(
                     Arg::with_name("quiet")
                         .short("q")
@@ -219,41 +456,269 @@ fn main() {
         .subcommand(
             SubCommand::with_name("format")
                 .about("Insert a license header in each file")
-                .arg(Arg::with_name("path").help("a file path").takes_value(true).required(true))
+                .arg(Arg::with_name("path").help("a file or directory path").takes_value(true).required(true))
                 .arg(
                     Arg::with_name("quiet")
                         .short("q")
                         .long("quiet")
                         .help("no stdout / stderr"),
+                )
+                .arg(
+                    Arg::with_name("write")
+                        .short("w")
+                        .long("write")
+                        .help("rewrite each file in place (atomically)"),
+                )
+                .arg(
+                    Arg::with_name("stdout")
+                        .long("stdout")
+                        .help("print the rewritten file to stdout (default when --write/--check are absent; ignored with --check)"),
+                )
+                .arg(
+                    Arg::with_name("check")
+                        .long("check")
+                        .help("exit non-zero if any file would change, without modifying anything"),
                 ),
         )
+        .subcommand(
+            SubCommand::with_name("deps")
+                .about("Check dependency licenses against the [allowed] / [exceptions] policy"),
+        )
         .get_matches();
 
     let config_path = matches.value_of("config").unwrap();
     let config_string = std::fs::read_to_string(config_path).expect("cannot read the config file");
-    let config: Config = toml::from_str(&config_string).expect("invalid toml");
+    let mut config: Config = toml::from_str(&config_string).expect("invalid toml");
+    resolve_template(&mut config);
+    if config.template.is_empty() {
+        eprintln!(
+            "error: no license template configured (set `template`, a readable `template_path`, or a known `spdx` id)"
+        );
+        std::process::exit(1);
+    }
 
     // You can handle information about subcommands by requesting their matches by name
     // (as below), requesting just the name used, or both at the same time
     if let Some(matches) = matches.subcommand_matches("lint") {
         let path = matches.value_of("path").unwrap();
-        let res = lint(&config, path);
-        if res.is_err() {
-            if !matches.is_present("quiet") {
-                eprintln!("Error in {}\n{}", path, res.unwrap_err());
+        let files = walk_files(&config, path).expect("failed to walk path");
+        let results: Vec<(PathBuf, Result<(), Error>)> = files
+            .into_par_iter()
+            .map(|file| {
+                let result = lint(&config, &file);
+                (file, result)
+            })
+            .collect();
+
+        let mut had_error = false;
+        for (file, result) in results {
+            if let Err(e) = result {
+                had_error = true;
+                if !matches.is_present("quiet") {
+                    eprintln!("Error in {}\n{}", file.display(), e);
+                }
             }
+        }
+        if had_error {
             std::process::exit(1);
         }
     } else if let Some(matches) = matches.subcommand_matches("format") {
         let path = matches.value_of("path").unwrap();
-        let res = format(&config, path);
-        if res.is_err() {
-            if !matches.is_present("quiet") {
-                eprintln!("Error in {}\n{}", path, res.unwrap_err());
+        let do_check = matches.is_present("check");
+        let do_write = matches.is_present("write");
+        let do_stdout = matches.is_present("stdout") || (!do_check && !do_write);
+
+        let files = walk_files(&config, path).expect("failed to walk path");
+        let results: Vec<(PathBuf, Result<Option<String>, Error>)> = files
+            .into_par_iter()
+            .map(|file| {
+                let result = format(&config, &file);
+                (file, result)
+            })
+            .collect();
+
+        let mut had_error = false;
+        let mut changed = 0usize;
+        let total = results.len();
+        for (file, result) in results {
+            match result {
+                Ok(Some(new_contents)) => {
+                    changed += 1;
+                    if do_check {
+                        had_error = true;
+                        if !matches.is_present("quiet") {
+                            println!("would insert a license header: {}", file.display());
+                        }
+                    } else {
+                        if do_write {
+                            if let Err(e) = write_atomic(&file, &new_contents) {
+                                had_error = true;
+                                changed -= 1;
+                                if !matches.is_present("quiet") {
+                                    eprintln!("Error in {}\n{}", file.display(), e);
+                                }
+                            }
+                        }
+                        if do_stdout {
+                            println!("{}", new_contents);
+                        }
+                    }
+                }
+                Ok(None) => {}
+                Err(e) => {
+                    had_error = true;
+                    if !matches.is_present("quiet") {
+                        eprintln!("Error in {}\n{}", file.display(), e);
+                    }
+                }
             }
+        }
+        if !matches.is_present("quiet") && !do_check {
+            println!("Inserted a license header in {} of {} file(s)", changed, total);
+        }
+        if had_error {
+            std::process::exit(1);
+        }
+    } else if matches.subcommand_matches("deps").is_some() {
+        if let Err(e) = deps::check(&config) {
+            eprintln!("{}", e);
             std::process::exit(1);
         }
     } else {
         unreachable!();
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn test_config() -> Config {
+        Config {
+            template: String::new(),
+            template_path: None,
+            spdx: None,
+            newline_after_shebang: false,
+            newline_after_template: false,
+            placeholder_defaults: HashMap::new(),
+            exclude: Vec::new(),
+            include: Vec::new(),
+            allowed: Vec::new(),
+            exceptions: HashMap::new(),
+            author: author::AuthorConfig::default(),
+            langs: HashMap::new(),
+        }
+    }
+
+    #[test]
+    fn alias_placeholders_expand_to_known_patterns() {
+        let re = template_line_to_regex("Copyright {year} {author}");
+        assert_eq!(re, "Copyright \\d{4} \\w+([ \\t\\w]*\\w)?");
+    }
+
+    #[test]
+    fn non_alias_placeholder_is_grouped_so_trailing_literal_is_required() {
+        // Regression test: an ungrouped `foo|bar` would let the top-level `|`
+        // swallow the trailing literal `baz`, matching any line containing "foo".
+        let re = template_line_to_regex("{foo|bar}baz");
+        assert_eq!(re, "(?:foo|bar)baz");
+
+        let r = regex::Regex::new(&re).unwrap();
+        assert!(r.is_match("foobaz"));
+        assert!(r.is_match("barbaz"));
+        assert!(!r.is_match("foo"));
+        assert!(!r.is_match("foo and nothing else"));
+    }
+
+    #[test]
+    fn escaped_braces_and_backslash_are_literal() {
+        let tokens = parse_template_line("\\{not a placeholder\\} \\\\");
+        assert_eq!(tokens.len(), 1);
+        match &tokens[0] {
+            TemplateToken::Literal(s) => assert_eq!(s, "{not a placeholder} \\"),
+            TemplateToken::Placeholder(_) => panic!("expected a literal token"),
+        }
+    }
+
+    #[test]
+    fn walk_files_with_include_still_walks_unrelated_files() {
+        let dir = std::env::temp_dir().join(format!(
+            "license-checker-test-{}-{}",
+            std::process::id(),
+            line!()
+        ));
+        std::fs::create_dir_all(dir.join("vendor")).unwrap();
+        std::fs::write(dir.join("a.rs"), "fn main() {}").unwrap();
+        std::fs::write(dir.join("b.rs"), "fn other() {}").unwrap();
+        std::fs::write(dir.join("vendor/c.rs"), "fn vendored() {}").unwrap();
+        std::fs::write(dir.join(".gitignore"), "vendor/\n").unwrap();
+
+        let mut langs = HashMap::new();
+        langs.insert(
+            "rust".to_string(),
+            Lang { extensions: vec!["rs".to_string()], comment: "//".to_string() },
+        );
+        let config = Config {
+            exclude: vec!["vendor/**".to_string()],
+            include: vec!["vendor/c.rs".to_string()],
+            langs,
+            ..test_config()
+        };
+
+        let mut files = walk_files(&config, &dir).unwrap();
+        files.sort();
+        std::fs::remove_dir_all(&dir).unwrap();
+
+        // Both ordinary, unrelated files (`a.rs`, `b.rs`) and the
+        // explicitly re-included `vendor/c.rs` must come back; `include`
+        // must not collapse the walk down to only override matches.
+        let names: Vec<String> =
+            files.iter().map(|f| f.strip_prefix(&dir).unwrap().to_string_lossy().into_owned()).collect();
+        assert_eq!(names, vec!["a.rs".to_string(), "b.rs".to_string(), "vendor/c.rs".to_string()]);
+    }
+
+    #[test]
+    fn resolve_template_prefers_inline_template_over_spdx() {
+        let mut config = Config {
+            template: "inline template".to_string(),
+            spdx: Some("MIT".to_string()),
+            ..test_config()
+        };
+        resolve_template(&mut config);
+        assert_eq!(config.template, "inline template");
+    }
+
+    #[test]
+    fn resolve_template_prefers_template_path_over_spdx() {
+        let path = std::env::temp_dir().join(format!(
+            "license-checker-test-template-{}-{}",
+            std::process::id(),
+            line!()
+        ));
+        std::fs::write(&path, "from template_path").unwrap();
+
+        let mut config = Config {
+            template_path: Some(path.to_string_lossy().into_owned()),
+            spdx: Some("MIT".to_string()),
+            ..test_config()
+        };
+        resolve_template(&mut config);
+        std::fs::remove_file(&path).unwrap();
+
+        assert_eq!(config.template, "from template_path");
+    }
+
+    #[test]
+    fn resolve_template_falls_back_to_spdx() {
+        let mut config = Config { spdx: Some("MIT".to_string()), ..test_config() };
+        resolve_template(&mut config);
+        assert!(config.template.contains("MIT License"), "got: {}", config.template);
+    }
+
+    #[test]
+    fn resolve_template_leaves_template_empty_when_nothing_resolves() {
+        let mut config = test_config();
+        resolve_template(&mut config);
+        assert!(config.template.is_empty());
+    }
+}
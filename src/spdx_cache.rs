@@ -0,0 +1,19 @@
+//! Embedded cache of canonical SPDX license texts, compressed at build time
+//! (see `build.rs`) the same way cargo-deny ships `spdx_cache.bin.zstd`.
+
+use once_cell::sync::Lazy;
+use std::collections::HashMap;
+
+static CACHE: Lazy<HashMap<String, String>> = Lazy::new(|| {
+    let compressed = include_bytes!(concat!(env!("OUT_DIR"), "/spdx_cache.bin.zst"));
+    let json = zstd::decode_all(&compressed[..]).expect("corrupt embedded spdx cache");
+    serde_json::from_slice(&json).expect("invalid embedded spdx cache")
+});
+
+/// Looks up the canonical license text for an SPDX identifier (e.g. `"MIT"`,
+/// `"Apache-2.0"`), if we have it embedded. `{author}`/`{year}` placeholders
+/// in the returned text are substituted the same way as a user-supplied
+/// `template`.
+pub fn lookup(id: &str) -> Option<String> {
+    CACHE.get(id).cloned()
+}